@@ -1,5 +1,6 @@
 //! Wrapper arround the cpufreq fs
 #![feature(test)]
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
@@ -7,6 +8,43 @@ use std::path::Path;
 /// Cpufreq error type
 type CpuFreqError = Box<dyn std::error::Error>;
 
+/// Per-cpu `(min, max)` hardware frequency bounds, see [`CPU::hardware_limits`]
+pub type HardwareLimits = (HashMap<usize, u64>, HashMap<usize, u64>);
+
+/// A `1`/`0` style boolean as found throughout sysfs (e.g. `cpuN/online`,
+/// `smt/active`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoolNum(pub bool);
+
+impl std::str::FromStr for BoolNum {
+    type Err = std::io::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "1" => Ok(BoolNum(true)),
+            "0" => Ok(BoolNum(false)),
+            other => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("'{other}' is not a valid 1/0 boolean"),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for BoolNum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", if self.0 { "1" } else { "0" })
+    }
+}
+
+/// Outcome of a best-effort bulk operation across every online cpu
+#[derive(Debug)]
+pub struct BulkResult<T> {
+    /// Per-cpu values for cpus that succeeded
+    pub successes: HashMap<usize, T>,
+    /// `(cpu, error)` pairs for cpus that failed
+    pub errors: Vec<(usize, CpuFreqError)>,
+}
+
 /// Base cpufreq functionality for reading and writing on cpu variables
 pub trait CpuFreq {
     // Base path to be defined
@@ -78,10 +116,85 @@ pub trait CpuFreq {
         }
         Ok(())
     }
+    /// Get variables for all online cpus, without aborting on the first
+    /// cpu that fails to read
+    fn try_get_variable_all<T>(var: &str) -> Result<BulkResult<T>, CpuFreqError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + 'static,
+    {
+        let mut successes = HashMap::new();
+        let mut errors = Vec::new();
+        for cpu in Self::get_ranges("online")? {
+            match Self::get_variable(cpu, var) {
+                Ok(value) => {
+                    successes.insert(cpu, value);
+                }
+                Err(e) => errors.push((cpu, e)),
+            }
+        }
+        Ok(BulkResult { successes, errors })
+    }
+    /// Set variables for all online cpus, without aborting on the first
+    /// cpu that fails to write
+    fn try_set_variable_all(var: &str, data: &str) -> Result<BulkResult<()>, std::io::Error> {
+        let mut successes = HashMap::new();
+        let mut errors = Vec::new();
+        for cpu in Self::get_ranges("online")? {
+            let path = format!("cpu{cpu}/cpufreq/{var}");
+            match Self::write_file(&path, data) {
+                Ok(()) => {
+                    successes.insert(cpu, ());
+                }
+                Err(e) => errors.push((cpu, Box::new(e) as CpuFreqError)),
+            }
+        }
+        Ok(BulkResult { successes, errors })
+    }
+}
+
+/// Per-cpu state captured by [`CPU::snapshot`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuSnapshot {
+    online: bool,
+    governor: Option<String>,
+    min_freq: Option<u64>,
+    max_freq: Option<u64>,
+    energy_performance_preference: Option<String>,
+}
+
+/// intel_pstate-specific state captured by [`CPU::snapshot`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntelPstateSnapshot {
+    no_turbo: bool,
+}
+
+/// A point-in-time capture of the whole cpufreq state, see [`CPU::snapshot`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    cpus: HashMap<usize, CpuSnapshot>,
+    intel_pstate: Option<IntelPstateSnapshot>,
+}
+
+/// A single `/proc/stat` reading, holding `(idle, total)` jiffy counters
+/// per cpu. Diffed by [`CPU::utilization`] to get a utilization ratio.
+#[derive(Debug, Clone)]
+pub struct StatSample {
+    ticks: HashMap<usize, (u64, u64)>,
+}
+
+/// Read-only, cross-platform per-cpu frequency snapshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuFreqs {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub cur: Option<u64>,
 }
 
 /// CPU object
-pub struct CPU {}
+pub struct CPU {
+    initial: Snapshot,
+}
 
 impl CpuFreq for CPU {
     /// Base path for cpufreq
@@ -89,6 +202,45 @@ impl CpuFreq for CPU {
 }
 
 impl CPU {
+    fn capture_snapshot() -> Result<Snapshot, CpuFreqError> {
+        let pstate = IntelPstate::new().ok();
+        let mut cpus = HashMap::new();
+        for id in Self::get_ranges("present")? {
+            let online = id == 0
+                || Self::read_file(&format!("cpu{id}/online"))
+                    .ok()
+                    .and_then(|data| data.trim().parse::<BoolNum>().ok())
+                    .map(|b| b.0)
+                    .unwrap_or(false);
+            let (governor, min_freq, max_freq, energy_performance_preference) = if online {
+                (
+                    Self::get_variable(id, "scaling_governor").ok(),
+                    Self::get_variable(id, "scaling_min_freq").ok(),
+                    Self::get_variable(id, "scaling_max_freq").ok(),
+                    pstate
+                        .as_ref()
+                        .and_then(|p| p.energy_performance_preference(id).ok()),
+                )
+            } else {
+                (None, None, None, None)
+            };
+            cpus.insert(
+                id,
+                CpuSnapshot {
+                    online,
+                    governor,
+                    min_freq,
+                    max_freq,
+                    energy_performance_preference,
+                },
+            );
+        }
+        let intel_pstate = pstate
+            .as_ref()
+            .and_then(|p| p.no_turbo().ok())
+            .map(|no_turbo| IntelPstateSnapshot { no_turbo });
+        Ok(Snapshot { cpus, intel_pstate })
+    }
     /// Creates a new CPU
     ///
     /// # Example
@@ -114,7 +266,62 @@ impl CPU {
                 return Err(Box::new(err));
             }
         };
-        Ok(CPU {})
+        let initial = Self::capture_snapshot()?;
+        Ok(CPU { initial })
+    }
+    /// Capture the current state of every present cpu (online state,
+    /// governor, scaling min/max, and intel_pstate EPP/no_turbo where
+    /// applicable)
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let snapshot = cpu.snapshot().expect("Unable to snapshot cpu state");
+    /// ```
+    pub fn snapshot(&self) -> Result<Snapshot, CpuFreqError> {
+        Self::capture_snapshot()
+    }
+    /// Restore a previously captured [`Snapshot`]
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let snapshot = cpu.snapshot().unwrap();
+    /// cpu.restore(&snapshot).expect("Unable to restore cpu state");
+    /// ```
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<(), CpuFreqError> {
+        for (&id, state) in &snapshot.cpus {
+            if id != 0 {
+                if state.online {
+                    self.enable(id)?;
+                } else {
+                    self.disable(id)?;
+                    continue;
+                }
+            }
+            if let Some(max_freq) = state.max_freq {
+                Self::set_variable(id, "scaling_max_freq", &max_freq.to_string())?;
+            }
+            if let Some(min_freq) = state.min_freq {
+                Self::set_variable(id, "scaling_min_freq", &min_freq.to_string())?;
+            }
+            if let Some(governor) = &state.governor {
+                Self::set_variable(id, "scaling_governor", governor)?;
+            }
+            if let Some(epp) = &state.energy_performance_preference {
+                Self::set_variable(id, "energy_performance_preference", epp)?;
+            }
+        }
+        if let Some(pstate_state) = &snapshot.intel_pstate {
+            if let Ok(pstate) = IntelPstate::new() {
+                pstate.set_no_turbo(pstate_state.no_turbo)?;
+            }
+        }
+        Ok(())
     }
     /// Get online cpus
     ///
@@ -152,7 +359,8 @@ impl CPU {
     pub fn frequencies(&self) -> Result<HashMap<usize, u64>, CpuFreqError> {
         Ok(CPU::get_variable_all("scaling_cur_freq")?)
     }
-    /// Get online max_frequencies
+    /// Get the configured scaling max frequency of every online cpu
+    /// (`scaling_max_freq`)
     ///
     /// # Example
     /// ```
@@ -162,9 +370,10 @@ impl CPU {
     /// let freqs = cpu.max_frequencies().expect("Unable to read online max_frequencies");
     /// ```
     pub fn max_frequencies(&self) -> Result<HashMap<usize, u64>, CpuFreqError> {
-        Ok(CPU::get_variable_all("scaling_cur_freq")?)
+        Ok(CPU::get_variable_all("scaling_max_freq")?)
     }
-    /// Get online min_frequencies
+    /// Get the configured scaling min frequency of every online cpu
+    /// (`scaling_min_freq`)
     ///
     /// # Example
     /// ```
@@ -174,7 +383,49 @@ impl CPU {
     /// let freqs = cpu.min_frequencies().expect("Unable to read online min_frequencies");
     /// ```
     pub fn min_frequencies(&self) -> Result<HashMap<usize, u64>, CpuFreqError> {
-        Ok(CPU::get_variable_all("scaling_cur_freq")?)
+        Ok(CPU::get_variable_all("scaling_min_freq")?)
+    }
+    /// Get the hardware-capable max frequency of every online cpu
+    /// (`cpuinfo_max_freq`), which does not change with `scaling_max_freq`
+    /// writes and may differ between cores on heterogeneous clusters
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let freqs = cpu.cpuinfo_max_frequencies().expect("Unable to read cpuinfo_max_freq");
+    /// ```
+    pub fn cpuinfo_max_frequencies(&self) -> Result<HashMap<usize, u64>, CpuFreqError> {
+        Ok(CPU::get_variable_all("cpuinfo_max_freq")?)
+    }
+    /// Get the hardware-capable min frequency of every online cpu
+    /// (`cpuinfo_min_freq`)
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let freqs = cpu.cpuinfo_min_frequencies().expect("Unable to read cpuinfo_min_freq");
+    /// ```
+    pub fn cpuinfo_min_frequencies(&self) -> Result<HashMap<usize, u64>, CpuFreqError> {
+        Ok(CPU::get_variable_all("cpuinfo_min_freq")?)
+    }
+    /// Get the silicon's real, per-cpu `(min, max)` frequency range
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let (min, max) = cpu.hardware_limits().expect("Unable to read hardware limits");
+    /// ```
+    pub fn hardware_limits(&self) -> Result<HardwareLimits, CpuFreqError> {
+        Ok((
+            self.cpuinfo_min_frequencies()?,
+            self.cpuinfo_max_frequencies()?,
+        ))
     }
     /// Get online min_frequencies
     ///
@@ -246,6 +497,56 @@ impl CPU {
     pub fn set_governors(&self, gov: &str) -> Result<(), CpuFreqError> {
         Ok(CPU::set_variable_all("scaling_governor", &gov)?)
     }
+    /// Set online cpu frequencies best-effort, applying the setting to
+    /// every online cpu instead of aborting on the first one that rejects
+    /// it
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let result = cpu.try_set_frequencies("2300000").expect("Unable to set frequencies");
+    /// for (id, err) in result.errors {
+    ///     eprintln!("cpu{id} rejected the frequency: {err}");
+    /// }
+    /// ```
+    pub fn try_set_frequencies<T: ToString>(
+        &self,
+        freq: T,
+    ) -> Result<BulkResult<()>, CpuFreqError> {
+        let freq = freq.to_string();
+        let setspeed = CPU::try_set_variable_all("scaling_setspeed", &freq)?;
+        let max = CPU::try_set_variable_all("scaling_max_freq", &freq)?;
+        let min = CPU::try_set_variable_all("scaling_min_freq", &freq)?;
+        let mut errors = setspeed.errors;
+        errors.extend(max.errors);
+        errors.extend(min.errors);
+        let failed: HashSet<usize> = errors.iter().map(|(id, _)| *id).collect();
+        let successes = setspeed
+            .successes
+            .into_keys()
+            .filter(|id| !failed.contains(id))
+            .map(|id| (id, ()))
+            .collect();
+        Ok(BulkResult { successes, errors })
+    }
+    /// Set online cpu governors best-effort, recording which cpus rejected
+    /// the governor instead of aborting on the first failure
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let result = cpu.try_set_governors("ondemand").expect("Unable to set governors");
+    /// for (id, err) in result.errors {
+    ///     eprintln!("cpu{id} rejected the governor: {err}");
+    /// }
+    /// ```
+    pub fn try_set_governors(&self, gov: &str) -> Result<BulkResult<()>, CpuFreqError> {
+        Ok(CPU::try_set_variable_all("scaling_governor", gov)?)
+    }
     /// Enable one cpu
     ///
     /// # Example
@@ -256,7 +557,10 @@ impl CPU {
     /// let freqs = cpu.enable(5).expect("Unable enable cpu");
     /// ```
     pub fn enable(&self, id: usize) -> Result<(), CpuFreqError> {
-        Ok(CPU::write_file(&format!("cpu{id}/online"), "1")?)
+        Ok(CPU::write_file(
+            &format!("cpu{id}/online"),
+            &BoolNum(true).to_string(),
+        )?)
     }
     /// Disable one cpu
     ///
@@ -268,7 +572,23 @@ impl CPU {
     /// let freqs = cpu.disable(5).expect("Unable disable cpu");
     /// ```
     pub fn disable(&self, id: usize) -> Result<(), CpuFreqError> {
-        Ok(CPU::write_file(&format!("cpu{id}/online"), "0")?)
+        Ok(CPU::write_file(
+            &format!("cpu{id}/online"),
+            &BoolNum(false).to_string(),
+        )?)
+    }
+    /// Check whether a single cpu is online
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let online = cpu.is_online(5).expect("Unable to read online state");
+    /// ```
+    pub fn is_online(&self, id: usize) -> Result<bool, CpuFreqError> {
+        let data = CPU::read_file(&format!("cpu{id}/online"))?;
+        Ok(data.trim().parse::<BoolNum>()?.0)
     }
     /// Enable all present cpus
     ///
@@ -326,7 +646,147 @@ impl CPU {
         }
         Ok(())
     }
-    /// Reset cpu governor, max and min frequencies
+    /// Whether the kernel exposes the global SMT switch at `smt/control`
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let capable = cpu.smt_capable();
+    /// ```
+    pub fn smt_capable(&self) -> bool {
+        Path::new(Self::CPUFREQ_PATH).join("smt/control").exists()
+    }
+    /// Whether SMT is currently active, read from `smt/active`
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let active = cpu.smt_active().expect("Unable to read smt/active");
+    /// ```
+    pub fn smt_active(&self) -> Result<bool, CpuFreqError> {
+        let data = CPU::read_file("smt/active")?;
+        Ok(data.trim().parse::<BoolNum>()?.0)
+    }
+    /// Enable or disable SMT
+    ///
+    /// Writes `on`/`off` to `smt/control` when the kernel exposes it,
+    /// falling back to [`enable_all`](CPU::enable_all)/[`disable_hyperthread`](CPU::disable_hyperthread)
+    /// on kernels without the global switch.
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// cpu.set_smt(false).expect("Unable to disable smt");
+    /// ```
+    pub fn set_smt(&self, enable: bool) -> Result<(), CpuFreqError> {
+        if self.smt_capable() {
+            let value = if enable { "on" } else { "off" };
+            Ok(CPU::write_file("smt/control", value)?)
+        } else if enable {
+            self.enable_all()
+        } else {
+            self.disable_hyperthread()
+        }
+    }
+    /// Get a [`Cpu`] handle for every online logical cpu
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let cpus = cpu.cpus().expect("Unable to list cpus");
+    /// ```
+    pub fn cpus(&self) -> Result<Vec<Cpu>, CpuFreqError> {
+        Ok(CPU::get_ranges("online")?
+            .into_iter()
+            .map(|id| Cpu { id })
+            .collect())
+    }
+    /// Get a [`Policy`] handle for every `cpufreq/policyN` clock domain
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let policies = cpu.policies().expect("Unable to list policies");
+    /// ```
+    pub fn policies(&self) -> Result<Vec<Policy>, CpuFreqError> {
+        Self::list_policies()
+    }
+    fn list_policies() -> Result<Vec<Policy>, CpuFreqError> {
+        let path = Path::new(Self::CPUFREQ_PATH).join("cpufreq");
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let name = entry?.file_name();
+            if let Some(id) = name.to_string_lossy().strip_prefix("policy") {
+                if let Ok(id) = id.parse() {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids.into_iter().map(|id| Policy { id }).collect())
+    }
+    /// Best-effort, read-only per-cpu frequency query, portable across
+    /// platforms
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    ///
+    /// let freqs = CPU::freqs();
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn freqs() -> Vec<CpuFreqs> {
+        if let Ok(policies) = Self::list_policies() {
+            if !policies.is_empty() {
+                return policies
+                    .into_iter()
+                    .filter_map(|policy| {
+                        // Skip policies whose affected_cpus couldn't be read or
+                        // are empty instead of fabricating a phantom reading.
+                        let cpus = policy.affected_cpus().ok()?;
+                        if cpus.is_empty() {
+                            return None;
+                        }
+                        let freqs = CpuFreqs {
+                            min: policy.min_freq().ok(),
+                            max: policy.max_freq().ok(),
+                            cur: policy.cur_freq().ok(),
+                        };
+                        Some(vec![freqs; cpus.len()])
+                    })
+                    .flatten()
+                    .collect();
+            }
+        }
+        Self::get_ranges("present")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| CpuFreqs {
+                min: Self::get_variable(id, "scaling_min_freq").ok(),
+                max: Self::get_variable(id, "scaling_max_freq").ok(),
+                cur: Self::get_variable(id, "scaling_cur_freq").ok(),
+            })
+            .collect()
+    }
+    /// See the Linux implementation above
+    #[cfg(not(target_os = "linux"))]
+    pub fn freqs() -> Vec<CpuFreqs> {
+        let count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        vec![CpuFreqs::default(); count]
+    }
+    /// Reset the cpu to the state captured when this `CPU` was constructed
     ///
     /// # Example
     /// ```
@@ -336,14 +796,338 @@ impl CPU {
     /// let freqs = cpu.reset().expect("Unable to reset cpu");
     /// ```
     pub fn reset(&self) -> Result<(), CpuFreqError> {
-        self.enable_all()?;
-        self.set_governors("schedutil")?;
-        let avail_freqs = self.available_frequencies()?;
-        let max_freq = avail_freqs.get(&0).unwrap().iter().max().unwrap();
-        let min_freq = avail_freqs.get(&0).unwrap().iter().min().unwrap();
-        self.set_max_frequencies(max_freq)?;
-        self.set_min_frequencies(min_freq)?;
-        Ok(())
+        self.restore(&self.initial)
+    }
+    /// Take a `/proc/stat` sample, see [`CPU::utilization`]
+    pub fn sample_stat(&self) -> Result<StatSample, CpuFreqError> {
+        let data = fs::read_to_string("/proc/stat")?;
+        let mut ticks = HashMap::new();
+        for line in data.lines() {
+            let Some(rest) = line.strip_prefix("cpu") else {
+                continue;
+            };
+            let mut fields = rest.split_whitespace();
+            let Some(id) = fields.next().and_then(|x| x.parse::<usize>().ok()) else {
+                continue;
+            };
+            let fields: Vec<u64> = fields.filter_map(|x| x.parse().ok()).collect();
+            if fields.len() < 4 {
+                continue;
+            }
+            let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+            let total = fields.iter().sum();
+            ticks.insert(id, (idle, total));
+        }
+        Ok(StatSample { ticks })
+    }
+    /// Per-cpu utilization (`0.0..=1.0`) between two `/proc/stat` samples
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::CPU;
+    /// use std::time::Duration;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// let before = cpu.sample_stat().unwrap();
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// let after = cpu.sample_stat().unwrap();
+    /// let util = cpu.utilization(&before, &after).expect("Unable to compute utilization");
+    /// ```
+    pub fn utilization(
+        &self,
+        previous: &StatSample,
+        current: &StatSample,
+    ) -> Result<HashMap<usize, f64>, CpuFreqError> {
+        let online: HashSet<usize> = self.online()?.into_iter().collect();
+        let mut util = HashMap::new();
+        for (&id, &(idle, total)) in &current.ticks {
+            if !online.contains(&id) {
+                continue;
+            }
+            let Some(&(prev_idle, prev_total)) = previous.ticks.get(&id) else {
+                continue;
+            };
+            let total_delta = total.saturating_sub(prev_total);
+            if total_delta == 0 {
+                continue;
+            }
+            let idle_delta = idle.saturating_sub(prev_idle);
+            let busy_delta = total_delta.saturating_sub(idle_delta);
+            util.insert(id, busy_delta as f64 / total_delta as f64);
+        }
+        Ok(util)
+    }
+    /// Run a `userspace`-governor ondemand loop. Only returns when reading
+    /// or writing sysfs or `/proc/stat` fails; run it on its own thread.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use cpufreq::CPU;
+    /// use std::time::Duration;
+    ///
+    /// let cpu = CPU::new().unwrap();
+    /// cpu.run_ondemand(0.8, 0.2, Duration::from_secs(1)).unwrap();
+    /// ```
+    pub fn run_ondemand(
+        &self,
+        up_threshold: f64,
+        down_threshold: f64,
+        interval: std::time::Duration,
+    ) -> Result<(), CpuFreqError> {
+        let mut previous = self.sample_stat()?;
+        loop {
+            std::thread::sleep(interval);
+            let current = self.sample_stat()?;
+            let util = self.utilization(&previous, &current)?;
+            previous = current;
+            for policy in self.policies()? {
+                let cpus = policy.affected_cpus()?;
+                let samples: Vec<f64> =
+                    cpus.iter().filter_map(|id| util.get(id).copied()).collect();
+                if samples.is_empty() {
+                    continue;
+                }
+                let avg = samples.iter().sum::<f64>() / samples.len() as f64;
+                if avg < up_threshold && avg > down_threshold {
+                    continue;
+                }
+                let available: Vec<u64> =
+                    Self::get_variable::<String>(cpus[0], "scaling_available_frequencies")?
+                        .split_whitespace()
+                        .map(|x| x.parse())
+                        .collect::<Result<Vec<u64>, _>>()?;
+                let cur = policy.cur_freq()?;
+                let next = if avg >= up_threshold {
+                    available.iter().filter(|&&f| f > cur).min()
+                } else {
+                    available.iter().filter(|&&f| f < cur).max()
+                };
+                if let Some(&next) = next {
+                    policy.set_speed(next)?;
+                }
+            }
+        }
+    }
+}
+
+/// A single logical CPU core bound to `cpuN/cpufreq`
+pub struct Cpu {
+    id: usize,
+}
+
+impl Cpu {
+    /// The logical CPU id, e.g. `3` for `cpu3`
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Current frequency of this cpu
+    pub fn cur_freq(&self) -> Result<u64, CpuFreqError> {
+        CPU::get_variable(self.id, "scaling_cur_freq")
+    }
+    /// Configured minimum frequency of this cpu
+    pub fn min_freq(&self) -> Result<u64, CpuFreqError> {
+        CPU::get_variable(self.id, "scaling_min_freq")
+    }
+    /// Configured maximum frequency of this cpu
+    pub fn max_freq(&self) -> Result<u64, CpuFreqError> {
+        CPU::get_variable(self.id, "scaling_max_freq")
+    }
+    /// Set this cpu's minimum frequency
+    pub fn set_min_freq<T: ToString>(&self, freq: T) -> Result<(), CpuFreqError> {
+        Ok(CPU::set_variable(
+            self.id,
+            "scaling_min_freq",
+            &freq.to_string(),
+        )?)
+    }
+    /// Set this cpu's maximum frequency
+    pub fn set_max_freq<T: ToString>(&self, freq: T) -> Result<(), CpuFreqError> {
+        Ok(CPU::set_variable(
+            self.id,
+            "scaling_max_freq",
+            &freq.to_string(),
+        )?)
+    }
+    /// Current governor of this cpu
+    pub fn governor(&self) -> Result<String, CpuFreqError> {
+        CPU::get_variable(self.id, "scaling_governor")
+    }
+    /// Set this cpu's governor
+    pub fn set_governor(&self, gov: &str) -> Result<(), CpuFreqError> {
+        Ok(CPU::set_variable(self.id, "scaling_governor", gov)?)
+    }
+    /// Governors available for this cpu
+    pub fn available_governors(&self) -> Result<Vec<String>, CpuFreqError> {
+        let data: String = CPU::get_variable(self.id, "scaling_available_governors")?;
+        Ok(data.split_whitespace().map(String::from).collect())
+    }
+}
+
+/// A clock domain bound to `cpufreq/policyN`, shared by every cpu in
+/// `affected_cpus`/`related_cpus`
+pub struct Policy {
+    id: usize,
+}
+
+impl Policy {
+    fn read<T>(&self, var: &str) -> Result<T, CpuFreqError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + 'static,
+    {
+        let path = format!("cpufreq/policy{}/{}", self.id, var);
+        let data = CPU::read_file(&path)?;
+        Ok(data.trim().parse()?)
+    }
+    fn write(&self, var: &str, data: &str) -> Result<(), std::io::Error> {
+        let path = format!("cpufreq/policy{}/{}", self.id, var);
+        CPU::write_file(&path, data)
+    }
+    /// The policy id, e.g. `1` for `policy1`
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Current frequency of this policy
+    pub fn cur_freq(&self) -> Result<u64, CpuFreqError> {
+        self.read("scaling_cur_freq")
+    }
+    /// Configured minimum frequency of this policy
+    pub fn min_freq(&self) -> Result<u64, CpuFreqError> {
+        self.read("scaling_min_freq")
+    }
+    /// Configured maximum frequency of this policy
+    pub fn max_freq(&self) -> Result<u64, CpuFreqError> {
+        self.read("scaling_max_freq")
+    }
+    /// Set this policy's minimum frequency, affecting every cpu it covers
+    pub fn set_min_freq<T: ToString>(&self, freq: T) -> Result<(), CpuFreqError> {
+        Ok(self.write("scaling_min_freq", &freq.to_string())?)
+    }
+    /// Set this policy's maximum frequency, affecting every cpu it covers
+    pub fn set_max_freq<T: ToString>(&self, freq: T) -> Result<(), CpuFreqError> {
+        Ok(self.write("scaling_max_freq", &freq.to_string())?)
+    }
+    /// Set this policy's target frequency under the `userspace` governor,
+    /// affecting every cpu it covers
+    pub fn set_speed<T: ToString>(&self, freq: T) -> Result<(), CpuFreqError> {
+        Ok(self.write("scaling_setspeed", &freq.to_string())?)
+    }
+    /// Current governor of this policy
+    pub fn governor(&self) -> Result<String, CpuFreqError> {
+        self.read("scaling_governor")
+    }
+    /// Set this policy's governor, affecting every cpu it covers
+    pub fn set_governor(&self, gov: &str) -> Result<(), CpuFreqError> {
+        Ok(self.write("scaling_governor", gov)?)
+    }
+    /// Governors available for this policy
+    pub fn available_governors(&self) -> Result<Vec<String>, CpuFreqError> {
+        let data: String = self.read("scaling_available_governors")?;
+        Ok(data.split_whitespace().map(String::from).collect())
+    }
+    /// Logical cpus currently online and governed by this policy
+    pub fn affected_cpus(&self) -> Result<Vec<usize>, CpuFreqError> {
+        let data: String = self.read("affected_cpus")?;
+        Ok(data
+            .split_whitespace()
+            .map(|x| x.parse())
+            .collect::<Result<Vec<usize>, _>>()?)
+    }
+    /// Logical cpus related to this policy, including ones hotplugged out
+    pub fn related_cpus(&self) -> Result<Vec<usize>, CpuFreqError> {
+        let data: String = self.read("related_cpus")?;
+        Ok(data
+            .split_whitespace()
+            .map(|x| x.parse())
+            .collect::<Result<Vec<usize>, _>>()?)
+    }
+}
+
+/// View over the `intel_pstate`-specific sysfs knobs
+pub struct IntelPstate {}
+
+impl IntelPstate {
+    fn path(var: &str) -> String {
+        format!("intel_pstate/{var}")
+    }
+    /// Build an intel_pstate view, checking the detected driver first
+    ///
+    /// # Example
+    /// ```
+    /// use cpufreq::IntelPstate;
+    ///
+    /// let pstate = IntelPstate::new();
+    /// ```
+    pub fn new() -> Result<Self, CpuFreqError> {
+        let driver: String = CPU::get_variable(0, "scaling_driver")?;
+        if driver.trim() != "intel-pstate" {
+            let err = std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "intel_pstate is not the active scaling driver",
+            );
+            return Err(Box::new(err));
+        }
+        Ok(IntelPstate {})
+    }
+    /// Whether turbo boost is disabled
+    pub fn no_turbo(&self) -> Result<bool, CpuFreqError> {
+        let data = CPU::read_file(&Self::path("no_turbo"))?;
+        Ok(data.trim().parse::<BoolNum>()?.0)
+    }
+    /// Disable (`true`) or re-enable (`false`) turbo boost
+    pub fn set_no_turbo(&self, disable_turbo: bool) -> Result<(), CpuFreqError> {
+        Ok(CPU::write_file(
+            &Self::path("no_turbo"),
+            &BoolNum(disable_turbo).to_string(),
+        )?)
+    }
+    /// Minimum performance as a percentage of the maximum supported frequency
+    pub fn min_perf_pct(&self) -> Result<u8, CpuFreqError> {
+        let data = CPU::read_file(&Self::path("min_perf_pct"))?;
+        Ok(data.trim().parse()?)
+    }
+    /// Set the minimum performance percentage
+    pub fn set_min_perf_pct(&self, pct: u8) -> Result<(), CpuFreqError> {
+        Ok(CPU::write_file(
+            &Self::path("min_perf_pct"),
+            &pct.to_string(),
+        )?)
+    }
+    /// Maximum performance as a percentage of the maximum supported frequency
+    pub fn max_perf_pct(&self) -> Result<u8, CpuFreqError> {
+        let data = CPU::read_file(&Self::path("max_perf_pct"))?;
+        Ok(data.trim().parse()?)
+    }
+    /// Set the maximum performance percentage
+    pub fn set_max_perf_pct(&self, pct: u8) -> Result<(), CpuFreqError> {
+        Ok(CPU::write_file(
+            &Self::path("max_perf_pct"),
+            &pct.to_string(),
+        )?)
+    }
+    /// Energy/performance preference of a single cpu
+    pub fn energy_performance_preference(&self, id: usize) -> Result<String, CpuFreqError> {
+        CPU::get_variable(id, "energy_performance_preference")
+    }
+    /// Set the energy/performance preference of a single cpu
+    pub fn set_energy_performance_preference(
+        &self,
+        id: usize,
+        pref: &str,
+    ) -> Result<(), CpuFreqError> {
+        Ok(CPU::set_variable(
+            id,
+            "energy_performance_preference",
+            pref,
+        )?)
+    }
+    /// Energy/performance preferences available on a single cpu
+    pub fn energy_performance_available_preferences(
+        &self,
+        id: usize,
+    ) -> Result<Vec<String>, CpuFreqError> {
+        let data: String = CPU::get_variable(id, "energy_performance_available_preferences")?;
+        Ok(data.split_whitespace().map(String::from).collect())
     }
 }
 
@@ -371,6 +1155,44 @@ mod tests {
     test_method!(max_frequencies);
     test_method!(min_frequencies);
     test_method!(available_frequencies);
+    test_method!(cpus);
+    test_method!(policies);
+    test_method!(cpuinfo_max_frequencies);
+    test_method!(cpuinfo_min_frequencies);
+
+    #[test]
+    fn hardware_limits_bound_scaling_limits() {
+        let cpu = CPU::new().unwrap();
+        let (hw_min, hw_max) = cpu.hardware_limits().unwrap();
+        let max = cpu.max_frequencies().unwrap();
+        let min = cpu.min_frequencies().unwrap();
+        for (id, &scaling_max) in &max {
+            assert!(scaling_max <= hw_max[id]);
+        }
+        for (id, &scaling_min) in &min {
+            assert!(scaling_min >= hw_min[id]);
+        }
+    }
+
+    #[test]
+    fn per_cpu_matches_hashmap() {
+        let cpu = CPU::new().unwrap();
+        let governors = cpu.governors().unwrap();
+        for c in cpu.cpus().unwrap() {
+            assert_eq!(c.governor().unwrap(), governors[&c.id()]);
+        }
+    }
+
+    #[test]
+    fn policy_affected_cpus_are_online() {
+        let cpu = CPU::new().unwrap();
+        let online = cpu.online().unwrap();
+        for policy in cpu.policies().unwrap() {
+            for id in policy.affected_cpus().unwrap() {
+                assert!(online.contains(&id));
+            }
+        }
+    }
 
     #[test]
     fn disable() {
@@ -429,4 +1251,95 @@ mod tests {
         let cpu = CPU::new().unwrap();
         cpu.reset().unwrap();
     }
+    #[test]
+    fn snapshot_roundtrip() {
+        let cpu = CPU::new().unwrap();
+        let snapshot = cpu.snapshot().unwrap();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: Snapshot = serde_json::from_str(&json).unwrap();
+        cpu.restore(&restored).unwrap();
+    }
+    #[test]
+    fn utilization() {
+        let cpu = CPU::new().unwrap();
+        let before = cpu.sample_stat().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let after = cpu.sample_stat().unwrap();
+        let util = cpu.utilization(&before, &after).unwrap();
+        let online = cpu.online().unwrap();
+        for (id, ratio) in util {
+            assert!(online.contains(&id));
+            assert!((0.0..=1.0).contains(&ratio));
+        }
+    }
+    #[test]
+    fn try_set_governors() {
+        let cpu = CPU::new().unwrap();
+        let governor = cpu.governors().unwrap()[&0].clone();
+        let result = cpu.try_set_governors(&governor).unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.successes.len(), cpu.online().unwrap().len());
+    }
+    #[test]
+    fn try_get_variable_all() {
+        let cpu = CPU::new().unwrap();
+        let online = cpu.online().unwrap();
+
+        let result = CPU::try_get_variable_all::<String>("scaling_governor").unwrap();
+        assert_eq!(result.successes.len(), online.len());
+        assert!(result.errors.is_empty());
+
+        let result = CPU::try_get_variable_all::<String>("no_such_variable").unwrap();
+        assert!(result.successes.is_empty());
+        assert_eq!(result.errors.len(), online.len());
+    }
+    #[test]
+    fn freqs() {
+        let cpu = CPU::new().unwrap();
+        cpu.enable_all().unwrap();
+        let freqs = CPU::freqs();
+        assert_eq!(freqs.len(), cpu.online().unwrap().len());
+        for f in freqs {
+            assert!(f.cur.is_some());
+        }
+    }
+    #[test]
+    fn freqs_without_cpu_new() {
+        // The whole point of freqs() is not needing CPU::new() to succeed
+        // first, so call it standalone and make sure it degrades to `None`s
+        // rather than panicking, whatever the driver/sysfs layout is.
+        for f in CPU::freqs() {
+            assert_eq!(f.min.is_some(), f.cur.is_some());
+            assert_eq!(f.max.is_some(), f.cur.is_some());
+        }
+    }
+    #[test]
+    fn smt() {
+        let cpu = CPU::new().unwrap();
+        cpu.enable_all().unwrap();
+        cpu.set_smt(false).unwrap();
+        cpu.set_smt(true).unwrap();
+        cpu.enable_all().unwrap();
+    }
+    #[test]
+    fn bool_num() {
+        assert_eq!("1".parse::<BoolNum>().unwrap(), BoolNum(true));
+        assert_eq!("0".parse::<BoolNum>().unwrap(), BoolNum(false));
+        assert!("2".parse::<BoolNum>().is_err());
+        assert_eq!(BoolNum(true).to_string(), "1");
+        assert_eq!(BoolNum(false).to_string(), "0");
+    }
+    #[test]
+    fn intel_pstate() {
+        let pstate = match IntelPstate::new() {
+            Ok(pstate) => pstate,
+            Err(_) => return, // not running on intel_pstate
+        };
+        let no_turbo = pstate.no_turbo().unwrap();
+        pstate.set_no_turbo(!no_turbo).unwrap();
+        pstate.set_no_turbo(no_turbo).unwrap();
+        let min = pstate.min_perf_pct().unwrap();
+        let max = pstate.max_perf_pct().unwrap();
+        assert!(min <= max);
+    }
 }